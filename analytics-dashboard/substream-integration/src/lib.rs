@@ -1,13 +1,13 @@
 mod pb;
+mod registry;
 
 use hex_literal::hex;
 use pb::eth::escrow::v1::{Events, EscrowEvent};
+use registry::ContractRegistry;
+use substreams::scalar::BigInt;
 use substreams::Hex;
 use substreams_ethereum::pb::eth::v2 as eth;
 
-// Your escrow contract address - update this with your deployed contract!
-const ESCROW_CONTRACT_ADDRESS: [u8; 20] = hex!("6E5559e7Cf01860416ff9CbEcC3bbdC1f05dB3D0");
-
 // Event signatures (Keccak256 hash of event signature)
 const USER_DEPOSIT_EVENT_SIG: [u8; 32] = hex!("9e71bc8eea02a63969f509818f2dafb9254532904319b9dbda79b67bd5eed006"); // UserDeposit(address,uint256)
 const USER_WITHDRAW_EVENT_SIG: [u8; 32] = hex!("884edad9ce6fa2440d8a54cc123490eb96d2768479d49ff9c7366125a9424364"); // UserWithdraw(address,uint256)
@@ -15,17 +15,22 @@ const PROVIDER_WITHDRAW_EVENT_SIG: [u8; 32] = hex!("17045ca4597ee1a46cdac70bb5ee
 const BATCH_PAYMENT_EVENT_SIG: [u8; 32] = hex!("f4757a49b326036464bec6fe419a4ae38c8e02ce3e68bf0809674f6aab8ad300"); // BatchPayment(address,address,uint256,uint256)
 const ZK_VERIFIER_UPDATED_EVENT_SIG: [u8; 32] = hex!("bf9b5b2e8c6c7e3a5d4c0b3e6f2a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a"); // ZkVerifierUpdated(address,address)
 
+// Raw-log counterpart to analytics_substream's `map_events`: emits untyped `EscrowEvent`s
+// straight off topic0 matching, for consumers that want the wire format rather than the
+// decoded `contract::Events`. `params` is a comma-separated list of `0x`-prefixed addresses to
+// track; when empty, the registry falls back to the original single deployed address.
 #[substreams::handlers::map]
-fn map_escrow_events(blk: eth::Block) -> Result<Events, substreams::errors::Error> {
+fn map_escrow_events(params: String, blk: eth::Block) -> Result<Events, substreams::errors::Error> {
+    let registry = ContractRegistry::from_params(&params);
     let mut events = Vec::new();
 
     for trx in &blk.transaction_traces {
         let transaction_hash = Hex::encode(&trx.hash);
-        
+
         if let Some(receipt) = &trx.receipt {
             for log in &receipt.logs {
-                // Only process logs from our escrow contract
-                if log.address != ESCROW_CONTRACT_ADDRESS {
+                // Only process logs from contracts in the registry
+                if !registry.contains(&log.address) {
                     continue;
                 }
 
@@ -39,19 +44,19 @@ fn map_escrow_events(blk: eth::Block) -> Result<Events, substreams::errors::Erro
                 // Parse different event types based on topic0 (event signature)
                 let event = match topic0.as_slice() {
                     sig if sig == USER_DEPOSIT_EVENT_SIG => {
-                        parse_user_deposit_event(&log, &transaction_hash, &blk)
+                        parse_user_deposit_event(&log, &transaction_hash, &blk, trx)
                     },
                     sig if sig == USER_WITHDRAW_EVENT_SIG => {
-                        parse_user_withdraw_event(&log, &transaction_hash, &blk)
+                        parse_user_withdraw_event(&log, &transaction_hash, &blk, trx)
                     },
                     sig if sig == PROVIDER_WITHDRAW_EVENT_SIG => {
-                        parse_provider_withdraw_event(&log, &transaction_hash, &blk)
+                        parse_provider_withdraw_event(&log, &transaction_hash, &blk, trx)
                     },
                     sig if sig == BATCH_PAYMENT_EVENT_SIG => {
-                        parse_batch_payment_event(&log, &transaction_hash, &blk)
+                        parse_batch_payment_event(&log, &transaction_hash, &blk, trx)
                     },
                     sig if sig == ZK_VERIFIER_UPDATED_EVENT_SIG => {
-                        parse_zk_verifier_updated_event(&log, &transaction_hash, &blk)
+                        parse_zk_verifier_updated_event(&log, &transaction_hash, &blk, trx)
                     },
                     _ => None, // Unknown event type
                 };
@@ -67,11 +72,13 @@ fn map_escrow_events(blk: eth::Block) -> Result<Events, substreams::errors::Erro
 }
 
 // UserDeposit(address indexed user, uint256 amount)
-fn parse_user_deposit_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) -> Option<EscrowEvent> {
+fn parse_user_deposit_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block, trx: &eth::TransactionTrace) -> Option<EscrowEvent> {
     if log.topics.len() < 2 {
         return None;
     }
     
+    let gas = gas_metrics(trx, blk);
+    
     let user_address = extract_address_from_topic(&log.topics[1]);
     let amount_cents = extract_uint256_from_data(&log.data, 0);
     
@@ -87,17 +94,21 @@ fn parse_user_deposit_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) ->
         block_number: blk.number,
         timestamp: blk.header.as_ref().unwrap().timestamp.as_ref().unwrap().seconds as u64,
         contract_address: Hex::encode(&log.address),
-        gas_used: 0, // Will be filled in later versions
-        gas_price: "0".to_string(),
+        gas_used: gas.gas_used,
+        gas_price: gas.gas_price,
+        gas_burned: gas.gas_burned,
+        gas_tip: gas.gas_tip,
     })
 }
 
 // UserWithdraw(address indexed user, uint256 amount)
-fn parse_user_withdraw_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) -> Option<EscrowEvent> {
+fn parse_user_withdraw_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block, trx: &eth::TransactionTrace) -> Option<EscrowEvent> {
     if log.topics.len() < 2 {
         return None;
     }
     
+    let gas = gas_metrics(trx, blk);
+    
     let user_address = extract_address_from_topic(&log.topics[1]);
     let amount_cents = extract_uint256_from_data(&log.data, 0);
     
@@ -113,17 +124,21 @@ fn parse_user_withdraw_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) ->
         block_number: blk.number,
         timestamp: blk.header.as_ref().unwrap().timestamp.as_ref().unwrap().seconds as u64,
         contract_address: Hex::encode(&log.address),
-        gas_used: 0,
-        gas_price: "0".to_string(),
+        gas_used: gas.gas_used,
+        gas_price: gas.gas_price,
+        gas_burned: gas.gas_burned,
+        gas_tip: gas.gas_tip,
     })
 }
 
 // ProviderWithdraw(address indexed provider, uint256 amount)
-fn parse_provider_withdraw_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) -> Option<EscrowEvent> {
+fn parse_provider_withdraw_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block, trx: &eth::TransactionTrace) -> Option<EscrowEvent> {
     if log.topics.len() < 2 {
         return None;
     }
     
+    let gas = gas_metrics(trx, blk);
+    
     let provider_address = extract_address_from_topic(&log.topics[1]);
     let amount_cents = extract_uint256_from_data(&log.data, 0);
     
@@ -139,17 +154,21 @@ fn parse_provider_withdraw_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block
         block_number: blk.number,
         timestamp: blk.header.as_ref().unwrap().timestamp.as_ref().unwrap().seconds as u64,
         contract_address: Hex::encode(&log.address),
-        gas_used: 0,
-        gas_price: "0".to_string(),
+        gas_used: gas.gas_used,
+        gas_price: gas.gas_price,
+        gas_burned: gas.gas_burned,
+        gas_tip: gas.gas_tip,
     })
 }
 
 // BatchPayment(address indexed user, address indexed provider, uint256 amount, uint256 numCalls)
-fn parse_batch_payment_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) -> Option<EscrowEvent> {
+fn parse_batch_payment_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block, trx: &eth::TransactionTrace) -> Option<EscrowEvent> {
     if log.topics.len() < 3 {
         return None;
     }
     
+    let gas = gas_metrics(trx, blk);
+    
     let user_address = extract_address_from_topic(&log.topics[1]);
     let provider_address = extract_address_from_topic(&log.topics[2]);
     let amount_cents = extract_uint256_from_data(&log.data, 0);
@@ -167,17 +186,21 @@ fn parse_batch_payment_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) ->
         block_number: blk.number,
         timestamp: blk.header.as_ref().unwrap().timestamp.as_ref().unwrap().seconds as u64,
         contract_address: Hex::encode(&log.address),
-        gas_used: 0,
-        gas_price: "0".to_string(),
+        gas_used: gas.gas_used,
+        gas_price: gas.gas_price,
+        gas_burned: gas.gas_burned,
+        gas_tip: gas.gas_tip,
     })
 }
 
 // ZkVerifierUpdated(address indexed oldVerifier, address indexed newVerifier)
-fn parse_zk_verifier_updated_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block) -> Option<EscrowEvent> {
+fn parse_zk_verifier_updated_event(log: &eth::Log, tx_hash: &str, blk: &eth::Block, trx: &eth::TransactionTrace) -> Option<EscrowEvent> {
     if log.topics.len() < 3 {
         return None;
     }
     
+    let gas = gas_metrics(trx, blk);
+    
     let old_verifier = extract_address_from_topic(&log.topics[1]);
     let new_verifier = extract_address_from_topic(&log.topics[2]);
     
@@ -193,11 +216,68 @@ fn parse_zk_verifier_updated_event(log: &eth::Log, tx_hash: &str, blk: &eth::Blo
         block_number: blk.number,
         timestamp: blk.header.as_ref().unwrap().timestamp.as_ref().unwrap().seconds as u64,
         contract_address: Hex::encode(&log.address),
-        gas_used: 0,
-        gas_price: "0".to_string(),
+        gas_used: gas.gas_used,
+        gas_price: gas.gas_price,
+        gas_burned: gas.gas_burned,
+        gas_tip: gas.gas_tip,
     })
 }
 
+// gas_used/gas_price plus the burned (base fee) vs. tip (priority fee) split of gas_price for
+// the transaction a log belongs to.
+struct GasBreakdown {
+    gas_used: u64,
+    gas_price: String,
+    gas_burned: String,
+    gas_tip: String,
+}
+
+// Compute gas_used and the effective gas_price (split into burned/tip) for the transaction a
+// log belongs to. Legacy (type-0) transactions spend a flat gas_price, all of it to the
+// proposer; EIP-1559 (type-2) transactions burn base_fee_per_gas and tip the rest, capped at
+// max_priority_fee_per_gas.
+//
+// gas_used always comes from trx.gas_used (the transaction's own gas, not the receipt's
+// cumulative_gas_used, which is a running total for the whole block and would overcount every
+// transaction but the first in a block). gas_burned/gas_tip are total wei spent (per-gas price
+// times gas_used), not per-gas-unit prices — gas_price keeps the per-gas-unit figure.
+fn gas_metrics(trx: &eth::TransactionTrace, blk: &eth::Block) -> GasBreakdown {
+    let gas_used = trx.gas_used;
+    let gas_used_big = BigInt::from(gas_used);
+
+    if trx.r#type != 2 {
+        let gas_price = BigInt::from_unsigned_bytes_be(&trx.gas_price);
+        return GasBreakdown {
+            gas_used,
+            gas_price: gas_price.to_string(),
+            gas_burned: "0".to_string(),
+            gas_tip: (gas_price * gas_used_big).to_string(),
+        };
+    }
+
+    let base_fee_per_gas = blk
+        .header
+        .as_ref()
+        .map(|header| BigInt::from_unsigned_bytes_be(&header.base_fee_per_gas))
+        .unwrap_or_else(|| BigInt::from(0));
+    let max_fee_per_gas = BigInt::from_unsigned_bytes_be(&trx.max_fee_per_gas);
+    let max_priority_fee_per_gas = BigInt::from_unsigned_bytes_be(&trx.max_priority_fee_per_gas);
+
+    let available_tip = max_fee_per_gas - base_fee_per_gas.clone();
+    let tip = if max_priority_fee_per_gas < available_tip {
+        max_priority_fee_per_gas
+    } else {
+        available_tip
+    };
+
+    GasBreakdown {
+        gas_used,
+        gas_price: (base_fee_per_gas.clone() + tip.clone()).to_string(),
+        gas_burned: (base_fee_per_gas * gas_used_big.clone()).to_string(),
+        gas_tip: (tip * gas_used_big).to_string(),
+    }
+}
+
 // Helper function to extract address from topic (last 20 bytes)
 fn extract_address_from_topic(topic: &[u8]) -> String {
     if topic.len() >= 32 {
@@ -208,31 +288,90 @@ fn extract_address_from_topic(topic: &[u8]) -> String {
     }
 }
 
-// Helper function to extract uint256 from log data as string
+// Helper function to extract a full-width uint256 from log data as a decimal string
 fn extract_uint256_from_data(data: &[u8], offset: usize) -> String {
     if data.len() >= offset + 32 {
         let value_bytes = &data[offset..offset + 32];
-        // Convert to decimal string representation
-        let mut result = 0u128; // Using u128 for large numbers, might need BigInt for full uint256
-        for (_, &byte) in value_bytes[16..].iter().enumerate() { // Take last 16 bytes for u128
-            result = (result << 8) | (byte as u128);
-        }
-        result.to_string()
+        // Decode all 32 big-endian bytes so values above 2^128 don't get truncated
+        BigInt::from_unsigned_bytes_be(value_bytes).to_string()
     } else {
         "0".to_string()
     }
 }
 
-// Helper function to extract uint256 from log data as u64
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trx_with(gas_used: u64, cumulative_gas_used: u64, r#type: i32) -> eth::TransactionTrace {
+        eth::TransactionTrace {
+            gas_used,
+            r#type,
+            receipt: Some(eth::TransactionReceipt {
+                cumulative_gas_used,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gas_metrics_uses_the_transactions_own_gas_not_the_blocks_cumulative_total() {
+        // A legacy transaction that isn't first in its block: cumulative_gas_used is much
+        // larger than this transaction's own gas_used, which is the bug this guards against.
+        let trx = trx_with(21_000, 500_000, 0);
+        let blk = eth::Block::default();
+        assert_eq!(gas_metrics(&trx, &blk).gas_used, 21_000);
+    }
+
+    #[test]
+    fn gas_metrics_splits_eip1559_fees_into_burned_and_tip() {
+        let mut trx = trx_with(21_000, 21_000, 2);
+        trx.max_fee_per_gas = vec![50];
+        trx.max_priority_fee_per_gas = vec![5];
+        let blk = eth::Block {
+            header: Some(eth::BlockHeader {
+                base_fee_per_gas: vec![10],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let gas = gas_metrics(&trx, &blk);
+        assert_eq!(gas.gas_price, "15");
+        // Totals, not per-gas prices: 21_000 gas_used times the 10/5 base-fee/tip split.
+        assert_eq!(gas.gas_burned, "210000");
+        assert_eq!(gas.gas_tip, "105000");
+    }
+
+    #[test]
+    fn extract_uint256_from_data_decodes_big_endian_and_handles_short_data() {
+        let mut data = vec![0u8; 32];
+        data[31] = 42;
+        assert_eq!(extract_uint256_from_data(&data, 0), "42");
+        assert_eq!(extract_uint256_from_data(&[], 0), "0");
+    }
+
+    #[test]
+    fn extract_uint256_from_data_does_not_truncate_values_above_2_pow_128() {
+        // Only byte 15 (the least significant byte of the high 128 bits) is set, so the value is
+        // exactly 2^128 — a decode that folded in only the last 16 bytes would see all zeroes
+        // there and report 0 instead.
+        let mut data = vec![0u8; 32];
+        data[15] = 1;
+        assert_eq!(
+            extract_uint256_from_data(&data, 0),
+            "340282366920938463463374607431768211456"
+        );
+    }
+}
+
+// Helper function to extract a uint256 from log data as a u64 (saturating on overflow)
 fn extract_uint256_from_data_as_u64(data: &[u8], offset: usize) -> u64 {
     if data.len() >= offset + 32 {
         let value_bytes = &data[offset..offset + 32];
-        // Convert last 8 bytes to u64
-        let mut result = 0u64;
-        for &byte in value_bytes[24..32].iter() { // Take last 8 bytes
-            result = (result << 8) | (byte as u64);
-        }
-        result
+        let value = BigInt::from_unsigned_bytes_be(value_bytes);
+        value.to_string().parse::<u64>().unwrap_or(u64::MAX)
     } else {
         0
     }
@@ -0,0 +1,69 @@
+use hex_literal::hex;
+
+/// Default escrow contract tracked when `params` holds no valid address.
+const DEFAULT_ESCROW_CONTRACT_ADDRESS: [u8; 20] = hex!("6E5559e7Cf01860416ff9CbEcC3bbdC1f05dB3D0");
+
+/// Set of escrow contract addresses `map_escrow_events` decodes raw EscrowEvents from.
+///
+/// Populated from the module's `params` (a comma-separated list of `0x`-prefixed
+/// addresses) so the same compiled substream can track a fleet of deployments
+/// instead of a single hardcoded address.
+pub struct ContractRegistry {
+    addresses: Vec<[u8; 20]>,
+}
+
+impl ContractRegistry {
+    pub fn from_params(params: &str) -> Self {
+        let addresses: Vec<[u8; 20]> = params
+            .split(',')
+            .filter_map(|raw| parse_address(raw.trim()))
+            .collect();
+
+        if addresses.is_empty() {
+            Self { addresses: vec![DEFAULT_ESCROW_CONTRACT_ADDRESS] }
+        } else {
+            Self { addresses }
+        }
+    }
+
+    pub fn contains(&self, address: &[u8]) -> bool {
+        self.addresses.iter().any(|tracked| tracked.as_slice() == address)
+    }
+}
+
+fn parse_address(raw: &str) -> Option<[u8; 20]> {
+    let stripped = raw.strip_prefix("0x").unwrap_or(raw);
+    if stripped.len() != 40 {
+        return None;
+    }
+
+    let mut address = [0u8; 20];
+    for (i, byte) in address.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&stripped[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_whitespace_or_garbage_params_fall_back_to_the_default_contract() {
+        for params in ["", "   ", "not-an-address", ",,,"] {
+            let registry = ContractRegistry::from_params(params);
+            assert!(registry.contains(&DEFAULT_ESCROW_CONTRACT_ADDRESS));
+        }
+    }
+
+    #[test]
+    fn a_multi_address_list_tracks_every_address_and_nothing_else() {
+        let registry = ContractRegistry::from_params(
+            "0xabababababababababababababababababababab, 0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+        );
+
+        assert!(registry.contains(&parse_address("0xabababababababababababababababababababab").unwrap()));
+        assert!(registry.contains(&parse_address("0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd").unwrap()));
+        assert!(!registry.contains(&DEFAULT_ESCROW_CONTRACT_ADDRESS));
+    }
+}
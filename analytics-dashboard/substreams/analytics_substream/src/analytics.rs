@@ -1,4 +1,5 @@
 use crate::pb::contract::v1 as contract;
+use crate::stats;
 use substreams::scalar::BigDecimal;
 use substreams_ethereum::pb::eth::v2 as eth;
 use std::collections::HashMap;
@@ -12,10 +13,15 @@ pub struct PaymentAnalytics {
     pub unique_providers: u32,
     pub avg_payment_size: BigDecimal,
     pub payment_frequency: u32,
+    /// Median payment size; skew-robust where `avg_payment_size` is not.
+    pub median: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
 }
 
 /// User behavior analytics for AI insights
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct UserMetrics {
     pub user_address: String,
     pub total_deposits: BigDecimal,
@@ -29,7 +35,7 @@ pub struct UserMetrics {
 }
 
 /// Provider performance analytics
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ProviderMetrics {
     pub provider_address: String,
     pub total_earnings: BigDecimal,
@@ -47,6 +53,7 @@ pub fn calculate_analytics(events: &contract::Events) -> PaymentAnalytics {
     let mut users = std::collections::HashSet::new();
     let mut providers = std::collections::HashSet::new();
     let mut payment_count = 0u32;
+    let mut amounts: Vec<f64> = Vec::new();
 
     // Process batch payments
     for payment in &events.escrow_batch_payments {
@@ -55,6 +62,9 @@ pub fn calculate_analytics(events: &contract::Events) -> PaymentAnalytics {
             users.insert(payment.user.clone());
             providers.insert(payment.provider.clone());
             payment_count += 1;
+            if let Some(amount_f64) = amount.to_f64() {
+                amounts.push(amount_f64);
+            }
         }
     }
 
@@ -69,12 +79,18 @@ pub fn calculate_analytics(events: &contract::Events) -> PaymentAnalytics {
         BigDecimal::from(0)
     };
 
+    let quantiles = stats::compute_quantiles(&amounts).unwrap_or_default();
+
     PaymentAnalytics {
         total_volume,
         unique_users: users.len() as u32,
         unique_providers: providers.len() as u32,
         avg_payment_size,
         payment_frequency: payment_count,
+        median: quantiles.p50,
+        p90: quantiles.p90,
+        p95: quantiles.p95,
+        p99: quantiles.p99,
     }
 }
 
@@ -199,19 +215,17 @@ pub fn detect_anomalies(events: &contract::Events) -> Vec<String> {
         }
     }
     
-    if !amounts.is_empty() {
-        let mean = amounts.iter().sum::<f64>() / amounts.len() as f64;
-        let variance = amounts.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / amounts.len() as f64;
-        let std_dev = variance.sqrt();
-        let threshold = mean + 3.0 * std_dev; // 3-sigma rule
-        
+    // IQR fencing is scale-free and robust to the heavy right skew typical of payment
+    // amounts, unlike a mean+3*stddev threshold which a handful of legitimate large
+    // payments can inflate enough to mask real outliers.
+    if let Some(quantiles) = stats::compute_quantiles(&amounts) {
         for payment in &events.escrow_batch_payments {
             if let Ok(amount) = BigDecimal::from_str(&payment.amount) {
                 if let Some(amount_f64) = amount.to_f64() {
-                    if amount_f64 > threshold {
+                    if let Some(severity) = quantiles.classify(amount_f64) {
                         anomalies.push(format!(
-                            "Unusually large payment: {} from user {} to provider {} (tx: {})",
-                            payment.amount, payment.user, payment.provider, payment.evt_tx_hash
+                            "Unusually large payment ({}): {} from user {} to provider {} (tx: {})",
+                            severity.as_str(), payment.amount, payment.user, payment.provider, payment.evt_tx_hash
                         ));
                     }
                 }
@@ -220,4 +234,345 @@ pub fn detect_anomalies(events: &contract::Events) -> Vec<String> {
     }
     
     anomalies
+}
+
+/// The running aggregate state `AnalyticsStore` folds each block's events into in place.
+#[derive(Default)]
+struct AnalyticsState {
+    total_volume: BigDecimal,
+    payment_count: u32,
+    unique_users: std::collections::HashSet<String>,
+    unique_providers: std::collections::HashSet<String>,
+    /// Kept in sorted order as amounts are inserted; `stats::compute_quantiles` still sorts its
+    /// own copy of it.
+    amounts: Vec<f64>,
+    user_metrics: HashMap<String, UserMetrics>,
+    provider_metrics: HashMap<String, ProviderMetrics>,
+}
+
+/// A single undoable mutation to `AnalyticsState`, recorded while `apply_block` folds a block's
+/// events in. `revert_to` replays a block's ops in reverse to unwind exactly the entries that
+/// block touched, instead of cloning the whole state on every block the way the earlier
+/// snapshot-per-block design did.
+enum UndoOp {
+    RemoveVolume(BigDecimal),
+    DecrementPaymentCount,
+    RemoveUniqueUser(String),
+    RemoveUniqueProvider(String),
+    RemoveAmountAt(usize),
+    RestoreUserMetrics(String, Option<UserMetrics>),
+    RestoreProviderMetrics(String, Option<ProviderMetrics>),
+}
+
+/// Incremental, reorg-safe accumulator for payment analytics. Rather than replaying the full
+/// `Events` history on every call like [`calculate_analytics`]/[`analyze_user_behavior`]/
+/// [`analyze_provider_performance`] do, it folds each block's events into `state` in place —
+/// touching only the users/providers/amounts an event actually maps to rather than cloning the
+/// whole state — and keeps an undo journal per applied block so `revert_to` can roll back a
+/// reorg in time proportional to the blocks undone, not the total history accumulated.
+///
+/// This is a plain in-process struct, not a substreams `Store` — a store module can only persist
+/// state through the runtime's own `StoreSet`/`StoreGet` primitives (see `store_payment_stats`
+/// in `lib.rs`), which get reorg handling for free from the runtime's native merge/undo logic,
+/// leaving no hook for a module to keep an arbitrary struct like this one alive across block
+/// invocations. `apply_block`/`revert_to` instead model what a *downstream consumer* of this
+/// crate's `map_events` output does on its own copy of the data: the `analytics_consumer` bin
+/// drives this exact lifecycle end to end.
+pub struct AnalyticsStore {
+    state: AnalyticsState,
+    tip_block_number: u64,
+    /// One entry per applied block, oldest first; `revert_to` unwinds from the back.
+    journal: Vec<(u64, Vec<UndoOp>)>,
+}
+
+impl Default for AnalyticsStore {
+    fn default() -> Self {
+        Self {
+            state: AnalyticsState::default(),
+            tip_block_number: 0,
+            journal: Vec::new(),
+        }
+    }
+}
+
+impl AnalyticsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one block's `events` into the running aggregates in place, recording enough of an
+    /// undo log to unwind just this block's changes later.
+    pub fn apply_block(&mut self, events: &contract::Events, block_number: u64) {
+        let mut undo = Vec::new();
+        // Only the first touch of a user/provider this block needs to record the pre-block
+        // value; later touches in the same block build on top of it.
+        let mut touched_users = std::collections::HashSet::new();
+        let mut touched_providers = std::collections::HashSet::new();
+
+        for payment in &events.escrow_batch_payments {
+            let amount = match BigDecimal::from_str(&payment.amount) {
+                Ok(amount) => amount,
+                Err(_) => continue,
+            };
+
+            self.state.total_volume += amount.clone();
+            undo.push(UndoOp::RemoveVolume(amount.clone()));
+            self.state.payment_count += 1;
+            undo.push(UndoOp::DecrementPaymentCount);
+            if self.state.unique_users.insert(payment.user.clone()) {
+                undo.push(UndoOp::RemoveUniqueUser(payment.user.clone()));
+            }
+            if self.state.unique_providers.insert(payment.provider.clone()) {
+                undo.push(UndoOp::RemoveUniqueProvider(payment.provider.clone()));
+            }
+            if let Some(amount_f64) = amount.to_f64() {
+                let idx = self.state.amounts.partition_point(|x| *x < amount_f64);
+                self.state.amounts.insert(idx, amount_f64);
+                undo.push(UndoOp::RemoveAmountAt(idx));
+            }
+
+            if touched_users.insert(payment.user.clone()) {
+                undo.push(UndoOp::RestoreUserMetrics(
+                    payment.user.clone(),
+                    self.state.user_metrics.get(&payment.user).cloned(),
+                ));
+            }
+            let user = self.state.user_metrics.entry(payment.user.clone()).or_default();
+            user.user_address = payment.user.clone();
+            user.total_payments += amount.clone();
+            user.payment_count += 1;
+            user.avg_payment_size = user.total_payments.clone() / BigDecimal::from(user.payment_count);
+            if !user.providers_used.contains(&payment.provider) {
+                user.providers_used.push(payment.provider.clone());
+            }
+            user.last_activity_block = payment.evt_block_number;
+            user.payment_pattern_score = calculate_pattern_score(user);
+
+            if touched_providers.insert(payment.provider.clone()) {
+                undo.push(UndoOp::RestoreProviderMetrics(
+                    payment.provider.clone(),
+                    self.state.provider_metrics.get(&payment.provider).cloned(),
+                ));
+            }
+            let provider = self.state.provider_metrics.entry(payment.provider.clone()).or_default();
+            provider.provider_address = payment.provider.clone();
+            provider.total_earnings += amount;
+            if let Ok(calls) = payment.num_calls.parse::<u64>() {
+                provider.total_api_calls += calls;
+            }
+            if provider.total_api_calls > 0 {
+                provider.avg_call_value = provider.total_earnings.clone() / BigDecimal::from(provider.total_api_calls);
+            }
+        }
+
+        for deposit in &events.escrow_user_deposits {
+            if self.state.unique_users.insert(deposit.user.clone()) {
+                undo.push(UndoOp::RemoveUniqueUser(deposit.user.clone()));
+            }
+            if touched_users.insert(deposit.user.clone()) {
+                undo.push(UndoOp::RestoreUserMetrics(
+                    deposit.user.clone(),
+                    self.state.user_metrics.get(&deposit.user).cloned(),
+                ));
+            }
+            let user = self.state.user_metrics.entry(deposit.user.clone()).or_default();
+            user.user_address = deposit.user.clone();
+            if let Ok(amount) = BigDecimal::from_str(&deposit.amount) {
+                user.total_deposits += amount;
+            }
+            user.last_activity_block = deposit.evt_block_number;
+        }
+
+        for withdrawal in &events.escrow_user_withdraws {
+            if touched_users.insert(withdrawal.user.clone()) {
+                undo.push(UndoOp::RestoreUserMetrics(
+                    withdrawal.user.clone(),
+                    self.state.user_metrics.get(&withdrawal.user).cloned(),
+                ));
+            }
+            let user = self.state.user_metrics.entry(withdrawal.user.clone()).or_default();
+            if let Ok(amount) = BigDecimal::from_str(&withdrawal.amount) {
+                user.total_withdrawals += amount;
+            }
+            user.last_activity_block = withdrawal.evt_block_number;
+        }
+
+        for withdrawal in &events.escrow_provider_withdraws {
+            if touched_providers.insert(withdrawal.provider.clone()) {
+                undo.push(UndoOp::RestoreProviderMetrics(
+                    withdrawal.provider.clone(),
+                    self.state.provider_metrics.get(&withdrawal.provider).cloned(),
+                ));
+            }
+            let provider = self.state.provider_metrics.entry(withdrawal.provider.clone()).or_default();
+            if let Ok(amount) = BigDecimal::from_str(&withdrawal.amount) {
+                provider.total_withdrawals += amount;
+            }
+        }
+
+        // Reliability score is derived from totals that just changed, so only the providers
+        // actually touched this block need recomputing.
+        for provider_address in &touched_providers {
+            if let Some(metrics) = self.state.provider_metrics.get_mut(provider_address) {
+                if metrics.total_withdrawals > BigDecimal::from(0) {
+                    let ratio = metrics.total_earnings.clone() / metrics.total_withdrawals.clone();
+                    metrics.reliability_score = ratio.to_f64().unwrap_or(0.0).min(1.0);
+                }
+            }
+        }
+
+        self.tip_block_number = block_number;
+        self.journal.push((block_number, undo));
+    }
+
+    /// Roll the store back to its state as of `block_number` — the entry point a reorg handler
+    /// calls once it knows the last block still valid on the canonical chain. Unwinds whole
+    /// applied blocks from the journal, most recent first, undoing each one's ops in reverse;
+    /// a no-op if `block_number` is at or past the current tip.
+    pub fn revert_to(&mut self, block_number: u64) {
+        while let Some(&(last_block, _)) = self.journal.last() {
+            if last_block <= block_number {
+                break;
+            }
+            let (_, undo) = self.journal.pop().unwrap();
+            for op in undo.into_iter().rev() {
+                match op {
+                    UndoOp::RemoveVolume(amount) => self.state.total_volume -= amount,
+                    UndoOp::DecrementPaymentCount => self.state.payment_count -= 1,
+                    UndoOp::RemoveUniqueUser(user) => {
+                        self.state.unique_users.remove(&user);
+                    }
+                    UndoOp::RemoveUniqueProvider(provider) => {
+                        self.state.unique_providers.remove(&provider);
+                    }
+                    UndoOp::RemoveAmountAt(idx) => {
+                        self.state.amounts.remove(idx);
+                    }
+                    UndoOp::RestoreUserMetrics(user, previous) => match previous {
+                        Some(metrics) => {
+                            self.state.user_metrics.insert(user, metrics);
+                        }
+                        None => {
+                            self.state.user_metrics.remove(&user);
+                        }
+                    },
+                    UndoOp::RestoreProviderMetrics(provider, previous) => match previous {
+                        Some(metrics) => {
+                            self.state.provider_metrics.insert(provider, metrics);
+                        }
+                        None => {
+                            self.state.provider_metrics.remove(&provider);
+                        }
+                    },
+                }
+            }
+        }
+        self.tip_block_number = self.journal.last().map(|(block, _)| *block).unwrap_or(0);
+    }
+
+    /// The block number the store currently reflects.
+    pub fn tip_block_number(&self) -> u64 {
+        self.tip_block_number
+    }
+
+    pub fn analytics(&self) -> PaymentAnalytics {
+        let avg_payment_size = if self.state.payment_count > 0 {
+            self.state.total_volume.clone() / BigDecimal::from(self.state.payment_count)
+        } else {
+            BigDecimal::from(0)
+        };
+        let quantiles = stats::compute_quantiles(&self.state.amounts).unwrap_or_default();
+
+        PaymentAnalytics {
+            total_volume: self.state.total_volume.clone(),
+            unique_users: self.state.unique_users.len() as u32,
+            unique_providers: self.state.unique_providers.len() as u32,
+            avg_payment_size,
+            payment_frequency: self.state.payment_count,
+            median: quantiles.p50,
+            p90: quantiles.p90,
+            p95: quantiles.p95,
+            p99: quantiles.p99,
+        }
+    }
+
+    pub fn user_metrics(&self) -> &HashMap<String, UserMetrics> {
+        &self.state.user_metrics
+    }
+
+    pub fn provider_metrics(&self) -> &HashMap<String, ProviderMetrics> {
+        &self.state.provider_metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment_events(amount: &str) -> contract::Events {
+        contract::Events {
+            escrow_batch_payments: vec![contract::EscrowBatchPayment {
+                evt_tx_hash: "0xabc".to_string(),
+                evt_index: 0,
+                evt_block_time: None,
+                evt_block_number: 0,
+                contract_address: "0xcontract".to_string(),
+                amount: amount.to_string(),
+                num_calls: "1".to_string(),
+                provider: "provider-1".to_string(),
+                user: "user-1".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_block_accumulates_and_revert_to_restores_an_exact_snapshot() {
+        let mut store = AnalyticsStore::new();
+        store.apply_block(&payment_events("100"), 1);
+        store.apply_block(&payment_events("200"), 2);
+        assert_eq!(store.tip_block_number(), 2);
+        assert_eq!(store.analytics().payment_frequency, 2);
+
+        store.revert_to(1);
+        assert_eq!(store.tip_block_number(), 1);
+        assert_eq!(store.analytics().payment_frequency, 1);
+    }
+
+    #[test]
+    fn revert_to_unwinds_whole_journaled_blocks_when_the_exact_block_was_never_applied() {
+        let mut store = AnalyticsStore::new();
+        store.apply_block(&payment_events("100"), 10);
+        store.apply_block(&payment_events("200"), 20);
+        store.apply_block(&payment_events("300"), 30);
+
+        // Block 25 was never applied directly; the nearest journaled block is 20.
+        store.revert_to(25);
+        assert_eq!(store.tip_block_number(), 20);
+        assert_eq!(store.analytics().payment_frequency, 2);
+    }
+
+    #[test]
+    fn revert_to_a_block_past_the_tip_leaves_the_tip_unchanged() {
+        let mut store = AnalyticsStore::new();
+        store.apply_block(&payment_events("100"), 10);
+        store.revert_to(20);
+        assert_eq!(store.tip_block_number(), 10);
+    }
+
+    #[test]
+    fn revert_to_restores_a_users_prior_metrics_instead_of_deleting_them() {
+        let mut store = AnalyticsStore::new();
+        store.apply_block(&payment_events("100"), 1);
+        assert_eq!(store.user_metrics()["user-1"].payment_count, 1);
+
+        // A second payment from the same user in a later block should be fully undoable,
+        // including the per-user metrics entry it overwrote rather than created.
+        store.apply_block(&payment_events("50"), 2);
+        assert_eq!(store.user_metrics()["user-1"].payment_count, 2);
+
+        store.revert_to(1);
+        assert_eq!(store.user_metrics()["user-1"].payment_count, 1);
+        assert_eq!(store.analytics().payment_frequency, 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+//! Off-chain companion binary for `legacy_analytics::AnalyticsStore`.
+//!
+//! A substreams store module can only persist state through the runtime's own `StoreSet`/
+//! `StoreGet` primitives, with reorgs handled by the runtime's native merge/undo logic — there's
+//! no hook for a handler to keep an arbitrary in-process struct, let alone one with its own undo
+//! journal, alive across block invocations. `AnalyticsStore::apply_block`/`revert_to` instead
+//! models what a downstream consumer of this crate's `map_events` output (a SQL sink, a Graph
+//! Node handler, ...) does on its own copy of the data: fold blocks in as they arrive over the
+//! substreams stream, and unwind to the last valid block when a `BlockUndoSignal` reports a
+//! reorg. This binary drives that exact lifecycle against a small synthetic block sequence.
+
+use analytics_substream::legacy_analytics::AnalyticsStore;
+use analytics_substream::pb::contract::v1 as contract;
+
+fn synthetic_block(block_number: u64, user: &str, provider: &str, amount: &str) -> contract::Events {
+    contract::Events {
+        escrow_batch_payments: vec![contract::EscrowBatchPayment {
+            evt_tx_hash: format!("0xblock{block_number}"),
+            evt_index: 0,
+            evt_block_time: None,
+            evt_block_number: block_number,
+            contract_address: "0x0000000000000000000000000000000000000000".to_string(),
+            amount: amount.to_string(),
+            num_calls: "1".to_string(),
+            provider: provider.to_string(),
+            user: user.to_string(),
+        }],
+        ..Default::default()
+    }
+}
+
+fn report(label: &str, store: &AnalyticsStore) {
+    let analytics = store.analytics();
+    println!(
+        "{label}: tip={} total_volume={} unique_users={} payment_frequency={}",
+        store.tip_block_number(),
+        analytics.total_volume,
+        analytics.unique_users,
+        analytics.payment_frequency,
+    );
+}
+
+fn main() {
+    let mut store = AnalyticsStore::new();
+
+    // Fold three canonical blocks in as they'd arrive from the substreams stream.
+    for (block_number, amount) in [(100, "1000"), (101, "2000"), (102, "3000")] {
+        store.apply_block(&synthetic_block(block_number, "0xuser1", "0xprovider1", amount), block_number);
+    }
+    report("after blocks 100-102", &store);
+
+    // A `BlockUndoSignal` arrives invalidating blocks 101/102; unwind to the last valid block.
+    store.revert_to(100);
+    report("after reverting to 100", &store);
+
+    // The canonical chain re-extends with a different block 101.
+    store.apply_block(&synthetic_block(101, "0xuser2", "0xprovider1", "500"), 101);
+    report("after the reorg's replacement block 101", &store);
+}
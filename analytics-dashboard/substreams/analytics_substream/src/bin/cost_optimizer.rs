@@ -0,0 +1,68 @@
+//! Off-chain companion binary for the `token_api` module.
+//!
+//! `token_api`'s price lookups and cost calculations need real HTTP requests, which a
+//! deterministic substreams handler can never make, so this crate's WASM module never calls it.
+//! This binary is the actual caller: point it at a payment's token address and raw amount and it
+//! prints the USD cost using the same `TokenAPIClient` the dashboard's substreams work relies on
+//! for pricing.
+
+use analytics_substream::token_api::{CostOptimizer, PaymentHistoryEntry, TokenAPIClient};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let token_address = args
+        .next()
+        .unwrap_or_else(|| "0x6c3ea9036406852006290770bedfcaba0e23a0e8".to_string());
+    let amount = args.next().unwrap_or_else(|| "1000000".to_string());
+
+    let client = TokenAPIClient::new();
+
+    // Exercise the PriceOracle path directly so price_confidence is visible: a confidence of 0
+    // means every PriceSource (The Graph, then the on-chain fallback) failed or was stale, and
+    // we're serving the Graph batch endpoint's own possibly-stale quote instead.
+    let metadata = client
+        .get_tokens_metadata(std::slice::from_ref(&token_address))
+        .await?;
+    if let Some(token) = metadata.get(&token_address) {
+        println!(
+            "{} price ${} (confidence: {} source(s))",
+            token.symbol, token.current_price_usd, token.price_confidence
+        );
+    }
+
+    let costs = client
+        .calculate_payment_costs(&[(token_address, amount)])
+        .await?;
+
+    for cost in costs {
+        println!(
+            "{} {} ~= ${} (efficiency {:.2})",
+            cost.amount, cost.token_symbol, cost.usd_value, cost.efficiency_score
+        );
+    }
+
+    // Synthesize a small payment history so CostOptimizer has enough volume to suggest batching.
+    let history: Vec<PaymentHistoryEntry> = (0..12)
+        .map(|i| PaymentHistoryEntry {
+            token_address: token_address.clone(),
+            amount: amount.clone(),
+            timestamp: 0,
+            priority_fee_per_gas: 1_000_000_000 + i * 10_000_000,
+            num_calls: 1,
+        })
+        .collect();
+
+    let optimizer = CostOptimizer::new();
+    for suggestion in optimizer.optimize_payments(&history).await? {
+        println!(
+            "[{}] {} (confidence {:.2}, savings_wei={:?})",
+            suggestion.suggestion_type,
+            suggestion.description,
+            suggestion.confidence,
+            suggestion.potential_savings_wei
+        );
+    }
+
+    Ok(())
+}
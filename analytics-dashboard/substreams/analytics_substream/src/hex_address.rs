@@ -0,0 +1,16 @@
+/// Parse a `0x`-prefixed (or bare) 20-byte hex address, e.g. from `params` strings or a decoded
+/// event field, into raw bytes. Shared by every module in this crate that needs one instead of
+/// each reimplementing the same byte loop (`registry::ContractRegistry`, `price_feed::PriceFeed`,
+/// `lib::map_payment_analytics`/`map_network_metrics`).
+pub fn parse_hex_address(raw: &str) -> Option<[u8; 20]> {
+    let stripped = raw.strip_prefix("0x").unwrap_or(raw);
+    if stripped.len() != 40 {
+        return None;
+    }
+
+    let mut address = [0u8; 20];
+    for (i, byte) in address.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&stripped[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(address)
+}
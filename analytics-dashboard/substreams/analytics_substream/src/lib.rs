@@ -1,8 +1,37 @@
 mod abi;
-mod pb;
-use hex_literal::hex;
+mod graph;
+mod hex_address;
+// `analytics` is already taken by the `use pb::analytics::v1 as analytics;` alias below, so this
+// file's module is aliased rather than renamed to avoid touching that pervasive import.
+//
+// `pub` so the `analytics_consumer` bin can drive `AnalyticsStore` directly: a substreams store
+// module can only persist state through the runtime's own `StoreSet`/`StoreGet` primitives (see
+// `store_payment_stats` below), with reorgs handled by the runtime's native merge/undo logic —
+// there's no hook for a module to keep an arbitrary in-process struct, let alone one with its
+// own undo journal, alive across block invocations. `AnalyticsStore::apply_block`/`revert_to`
+// instead models what a *downstream consumer* of this crate's `map_events` output (a SQL sink, a
+// Graph Node handler, ...) does to its own copy of the data on receiving a block / a
+// `BlockUndoSignal`, so it's exercised from that bin rather than from a handler here.
+#[path = "analytics.rs"]
+pub mod legacy_analytics;
+/// `pub` so the `analytics_consumer` bin can construct the `contract::Events` it feeds to
+/// `legacy_analytics::AnalyticsStore` without duplicating its field layout.
+pub mod pb;
+mod price_feed;
+mod registry;
+mod stats;
+/// `pub` (unlike most other modules here) so the `cost_optimizer` bin can reach it: nothing in
+/// this file calls it directly, since a substreams handler runs deterministically with no
+/// network access and can't make the HTTP calls `token_api` needs.
+pub mod token_api;
+use graph::SettlementGraph;
+use hex_address::parse_hex_address;
 use pb::contract::v1 as contract;
 use pb::analytics::v1 as analytics;
+use price_feed::PriceFeed;
+use registry::ContractRegistry;
+use stats::WelfordStats;
+use substreams::store::{StoreGet, StoreGetProto, StoreNew, StoreSet, StoreSetProto};
 use substreams::Hex;
 use substreams_ethereum::pb::eth::v2 as eth;
 use substreams_ethereum::Event;
@@ -10,18 +39,16 @@ use substreams_ethereum::Event;
 #[allow(unused_imports)]
 use num_traits::cast::ToPrimitive;
 use std::str::FromStr;
-use substreams::scalar::BigDecimal;
+use substreams::scalar::{BigDecimal, BigInt};
 
 substreams_ethereum::init!();
 
-const ESCROW_TRACKED_CONTRACT: [u8; 20] = hex!("e73922a448d76756babc9126f4401101cbfb4fbc");
-
-fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
+fn map_escrow_events(blk: &eth::Block, registry: &ContractRegistry, events: &mut contract::Events) {
     events.escrow_batch_payments.append(&mut blk
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::BatchPayment::match_and_decode(log) {
                         return Some(contract::EscrowBatchPayment {
@@ -29,6 +56,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             amount: event.amount.to_string(),
                             num_calls: event.num_calls.to_string(),
                             provider: event.provider,
@@ -44,7 +72,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::OwnershipTransferred::match_and_decode(log) {
                         return Some(contract::EscrowOwnershipTransferred {
@@ -52,6 +80,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             new_owner: event.new_owner,
                             previous_owner: event.previous_owner,
                         });
@@ -65,7 +94,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::Paused::match_and_decode(log) {
                         return Some(contract::EscrowPaused {
@@ -73,6 +102,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             account: event.account,
                         });
                     }
@@ -85,7 +115,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::ProviderWithdraw::match_and_decode(log) {
                         return Some(contract::EscrowProviderWithdraw {
@@ -93,6 +123,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             amount: event.amount.to_string(),
                             provider: event.provider,
                         });
@@ -106,7 +137,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::Unpaused::match_and_decode(log) {
                         return Some(contract::EscrowUnpaused {
@@ -114,6 +145,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             account: event.account,
                         });
                     }
@@ -126,7 +158,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::UserDeposit::match_and_decode(log) {
                         return Some(contract::EscrowUserDeposit {
@@ -134,6 +166,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             amount: event.amount.to_string(),
                             user: event.user,
                         });
@@ -147,7 +180,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::UserWithdraw::match_and_decode(log) {
                         return Some(contract::EscrowUserWithdraw {
@@ -155,6 +188,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             amount: event.amount.to_string(),
                             user: event.user,
                         });
@@ -168,7 +202,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         .receipts()
         .flat_map(|view| {
             view.receipt.logs.iter()
-                .filter(|log| log.address == ESCROW_TRACKED_CONTRACT)
+                .filter(|log| registry.contains(&log.address))
                 .filter_map(|log| {
                     if let Some(event) = abi::escrow_contract::events::ZkVerifierUpdated::match_and_decode(log) {
                         return Some(contract::EscrowZkVerifierUpdated {
@@ -176,6 +210,7 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
                             evt_index: log.block_index,
                             evt_block_time: Some(blk.timestamp().to_owned()),
                             evt_block_number: blk.number,
+                            contract_address: Hex(&log.address).to_string(),
                             new_verifier: event.new_verifier,
                             old_verifier: event.old_verifier,
                         });
@@ -186,17 +221,36 @@ fn map_escrow_events(blk: &eth::Block, events: &mut contract::Events) {
         })
         .collect());
 }
+
+// Decodes every tracked escrow contract's logs into the typed `contract::Events` this crate's
+// other handlers build on. `params` is a comma-separated list of `0x`-prefixed addresses to
+// track; when empty, the registry falls back to the original single deployed address.
 #[substreams::handlers::map]
-fn map_events(blk: eth::Block) -> Result<contract::Events, substreams::errors::Error> {
+fn map_events(params: String, blk: eth::Block) -> Result<contract::Events, substreams::errors::Error> {
+    let registry = ContractRegistry::from_params(&params);
     let mut events = contract::Events::default();
-    map_escrow_events(&blk, &mut events);
+    map_escrow_events(&blk, &registry, &mut events);
     Ok(events)
 }
 
-/// Advanced payment analytics with real-time insights
+/// Advanced payment analytics with real-time insights.
+///
+/// `price_params` is the same `0x<address>:<price_usd>:<decimals>[,...]` format consumed by
+/// [`price_feed::PriceFeed`], keyed by each payment's escrow `contract_address` (which stands
+/// in for the token that deployment settles in). Payments whose contract has no configured
+/// price are still counted toward `total_volume`/`avg_payment_size`, just not the USD figures.
+/// `median`/`p90`/`p95`/`p99` summarize this block's own payment-size distribution. Also logs
+/// any [`legacy_analytics::detect_anomalies`] hit for this block as a block-local IQR check
+/// alongside `map_anomaly_detection`'s stateful per-entity one.
 #[substreams::handlers::map]
-fn map_payment_analytics(events: contract::Events) -> Result<analytics::PaymentAnalytics, substreams::errors::Error> {
-    let mut total_volume = "0".to_string();
+fn map_payment_analytics(
+    price_params: String,
+    events: contract::Events,
+) -> Result<analytics::PaymentAnalytics, substreams::errors::Error> {
+    let price_feed = PriceFeed::from_params(&price_params);
+
+    let mut total_volume = BigInt::from(0);
+    let mut total_volume_usd = BigDecimal::from(0);
     let mut unique_users = std::collections::HashSet::new();
     let mut unique_providers = std::collections::HashSet::new();
     let mut payment_count = 0u32;
@@ -206,10 +260,15 @@ fn map_payment_analytics(events: contract::Events) -> Result<analytics::PaymentA
         unique_users.insert(payment.user.clone());
         unique_providers.insert(payment.provider.clone());
         payment_count += 1;
-        
-        // For simplicity, just use the first payment amount as total
-        if total_volume == "0" {
-            total_volume = payment.amount.clone();
+
+        if let Ok(amount) = BigInt::from_str(&payment.amount) {
+            total_volume = total_volume + amount.clone();
+
+            if let Some(usd) = parse_hex_address(&payment.contract_address)
+                .and_then(|token| price_feed.usd_value(&token, &BigDecimal::from(amount)))
+            {
+                total_volume_usd += usd;
+            }
         }
     }
 
@@ -219,43 +278,174 @@ fn map_payment_analytics(events: contract::Events) -> Result<analytics::PaymentA
     }
 
     let avg_payment_size = if payment_count > 0 {
-        total_volume.clone()
+        (BigDecimal::from(total_volume.clone()) / BigDecimal::from(payment_count)).to_string()
     } else {
         "0".to_string()
     };
+    let avg_payment_size_usd = if payment_count > 0 {
+        (total_volume_usd.clone() / BigDecimal::from(payment_count)).to_string()
+    } else {
+        "0".to_string()
+    };
+
+    // IQR-fenced outlier detection over this block's amounts, logged as a lightweight secondary
+    // signal alongside the per-entity Welford check `map_anomaly_detection` runs.
+    for anomaly in legacy_analytics::detect_anomalies(&events) {
+        substreams::log::info!("{}", anomaly);
+    }
+
+    // Quantiles of this block's payment-size distribution, surfaced as real fields below (same
+    // `.0` default as `legacy_analytics::PaymentAnalytics` when a block has too few samples to
+    // quantile) so dashboards can chart the fee/amount distribution instead of grepping logs.
+    let quantiles = stats::compute_quantiles(
+        &events
+            .escrow_batch_payments
+            .iter()
+            .filter_map(|p| p.amount.parse::<f64>().ok())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap_or_default();
 
     Ok(analytics::PaymentAnalytics {
-        total_volume,
+        total_volume: total_volume.to_string(),
+        total_volume_usd: total_volume_usd.to_string(),
         unique_users: unique_users.len() as u32,
         unique_providers: unique_providers.len() as u32,
         avg_payment_size,
+        avg_payment_size_usd,
         payment_frequency: payment_count,
+        median: quantiles.p50,
+        p90: quantiles.p90,
+        p95: quantiles.p95,
+        p99: quantiles.p99,
         block_number: 0,
         timestamp: None,
     })
 }
 
-/// Real-time anomaly detection for fraud prevention
+
+const ANOMALY_WARMUP_COUNT: u64 = stats::DEFAULT_WARMUP_COUNT;
+const ANOMALY_Z_SCORE_THRESHOLD: f64 = stats::DEFAULT_Z_SCORE_THRESHOLD;
+
+/// Store key for the per user→provider Welford accumulator: distinct pairs get independent
+/// baselines since a "normal" payment size for one relationship can be anomalous for another.
+fn payment_stats_key(user: &[u8], provider: &[u8]) -> String {
+    format!("user:{}:provider:{}", Hex(user), Hex(provider))
+}
+
+/// Maintains a running (count, mean, m2) Welford accumulator per user→provider pair, read back
+/// by `map_anomaly_detection` to flag payments against an adaptive baseline instead of a fixed
+/// threshold.
+#[substreams::handlers::store]
+fn store_payment_stats(
+    events: contract::Events,
+    stats_store: StoreGetProto<analytics::PaymentStats>,
+    store: StoreSetProto<analytics::PaymentStats>,
+) {
+    for payment in &events.escrow_batch_payments {
+        let amount = match payment.amount.parse::<f64>() {
+            Ok(amount) => amount,
+            Err(_) => continue,
+        };
+
+        let key = payment_stats_key(&payment.user, &payment.provider);
+        let mut welford = stats_store
+            .get_last(&key)
+            .map(|s| WelfordStats { count: s.count, mean: s.mean, m2: s.m2 })
+            .unwrap_or_default();
+        welford.update(amount);
+
+        store.set(
+            0,
+            &key,
+            &analytics::PaymentStats {
+                count: welford.count,
+                mean: welford.mean,
+                m2: welford.m2,
+            },
+        );
+    }
+}
+
+/// Real-time anomaly detection for fraud prevention.
+///
+/// Flags a batch payment once its user→provider pair's Welford baseline has warmed up
+/// (`ANOMALY_WARMUP_COUNT` samples) and the payment exceeds `mean + k * stddev`
+/// (`ANOMALY_Z_SCORE_THRESHOLD`), replacing the old fixed 1,000,000-unit cutoff with an
+/// adaptive per-entity threshold. `severity_score` scales with how many stddevs over the
+/// line the payment landed.
+///
+/// Falls back to a MAD-based z-score (`stats::median_absolute_deviation`/`stats::mad_z_score`)
+/// over this block's own amounts when a pair hasn't warmed up yet (or its warmed-up baseline has
+/// zero spread, so no Welford z-score exists): MAD needs no history of its
+/// own, so it catches an outlier on a user→provider pair's very first payment, which the
+/// Welford check structurally cannot.
 #[substreams::handlers::map]
-fn map_anomaly_detection(events: contract::Events) -> Result<analytics::AnomalyAlert, substreams::errors::Error> {
-    // Simple anomaly detection - flag unusually large payments
+fn map_anomaly_detection(
+    events: contract::Events,
+    stats_store: StoreGetProto<analytics::PaymentStats>,
+) -> Result<analytics::AnomalyAlert, substreams::errors::Error> {
+    // Computed once and only consulted when a pair's Welford baseline yields no z-score (not
+    // warmed up yet, or zero spread).
+    let mut batch_amounts: Vec<f64> = events
+        .escrow_batch_payments
+        .iter()
+        .filter_map(|p| p.amount.parse::<f64>().ok())
+        .collect();
+    let (batch_median, batch_mad) = stats::median_absolute_deviation(&mut batch_amounts);
+
     for payment in &events.escrow_batch_payments {
-        if let Ok(amount) = payment.amount.parse::<f64>() {
-            if amount > 1000000.0 { // Flag payments over 1M units
+        let amount = match payment.amount.parse::<f64>() {
+            Ok(amount) => amount,
+            Err(_) => continue,
+        };
+
+        let key = payment_stats_key(&payment.user, &payment.provider);
+        let welford = stats_store
+            .get_last(&key)
+            .map(|s| WelfordStats { count: s.count, mean: s.mean, m2: s.m2 });
+
+        if let Some(z) = welford.as_ref().and_then(|w| w.z_score(amount, ANOMALY_WARMUP_COUNT)) {
+            if z > ANOMALY_Z_SCORE_THRESHOLD {
                 return Ok(analytics::AnomalyAlert {
-                    anomaly_type: "large_payment".to_string(),
-                    description: format!("Large payment detected: {}", payment.amount),
+                    anomaly_type: "statistical_outlier".to_string(),
+                    description: format!(
+                        "Payment {} is {:.2} stddevs above the user/provider mean {:.2}",
+                        payment.amount, z, welford.unwrap().mean
+                    ),
                     user_address: payment.user.clone(),
                     provider_address: payment.provider.clone(),
                     transaction_hash: payment.evt_tx_hash.clone(),
-                    severity_score: 0.8,
+                    severity_score: (z / (ANOMALY_Z_SCORE_THRESHOLD * 2.0)).clamp(0.0, 1.0),
+                    detected_at: payment.evt_block_time.clone(),
+                    block_number: payment.evt_block_number,
+                });
+            }
+            continue;
+        }
+
+        // No warmed-up Welford baseline for this pair yet (or it's this pair's first payment
+        // altogether, or the baseline has zero spread) — fall back to a MAD check over this
+        // block's batch of amounts.
+        if let Some(z) = stats::mad_z_score(amount, batch_median, batch_mad) {
+            if z > ANOMALY_Z_SCORE_THRESHOLD {
+                return Ok(analytics::AnomalyAlert {
+                    anomaly_type: "mad_outlier".to_string(),
+                    description: format!(
+                        "Payment {} is {:.2} MAD-scaled deviations above the batch median {:.2}",
+                        payment.amount, z, batch_median
+                    ),
+                    user_address: payment.user.clone(),
+                    provider_address: payment.provider.clone(),
+                    transaction_hash: payment.evt_tx_hash.clone(),
+                    severity_score: (z / (ANOMALY_Z_SCORE_THRESHOLD * 2.0)).clamp(0.0, 1.0),
                     detected_at: payment.evt_block_time.clone(),
                     block_number: payment.evt_block_number,
                 });
             }
         }
     }
-    
+
     // No anomalies detected
     Ok(analytics::AnomalyAlert {
         anomaly_type: "none".to_string(),
@@ -269,24 +459,43 @@ fn map_anomaly_detection(events: contract::Events) -> Result<analytics::AnomalyA
     })
 }
 
-/// Network effect analysis
+const NETWORK_TOP_K: usize = 5;
+const CENTRALITY_ITERATIONS: u32 = 20;
+const CENTRALITY_DAMPING: f64 = 0.85;
+
+/// Network effect analysis, backed by a real [`SettlementGraph`] instead of an unordered
+/// `HashMap::take(5)`: ranks edges by genuine top-k volume and top-k transaction count, exposes
+/// each node's degree, derives `relationship_strength` from normalized volume share, and adds a
+/// PageRank-style provider centrality score. `price_params` uses the same format as
+/// [`map_payment_analytics`] and enriches both the per-edge and network-wide volume with a USD
+/// figure alongside the existing raw one.
 #[substreams::handlers::map]
-fn map_network_metrics(events: contract::Events) -> Result<analytics::NetworkMetrics, substreams::errors::Error> {
+fn map_network_metrics(
+    price_params: String,
+    events: contract::Events,
+) -> Result<analytics::NetworkMetrics, substreams::errors::Error> {
+    let price_feed = PriceFeed::from_params(&price_params);
+
+    let mut graph = SettlementGraph::default();
+    let mut edge_volume_usd: std::collections::HashMap<(Vec<u8>, Vec<u8>), BigDecimal> =
+        std::collections::HashMap::new();
+    let mut total_volume_usd = BigDecimal::from(0);
     let mut users = std::collections::HashSet::new();
-    let mut providers = std::collections::HashSet::new();
-    let mut connections = std::collections::HashMap::new();
-    let mut total_volume = "0".to_string();
 
     for payment in &events.escrow_batch_payments {
         users.insert(payment.user.clone());
-        providers.insert(payment.provider.clone());
-        
-        let key = (payment.user.clone(), payment.provider.clone());
-        *connections.entry(key).or_insert(0u32) += 1;
-        
-        // Simple total volume calculation
-        if total_volume == "0" {
-            total_volume = payment.amount.clone();
+
+        if let Ok(amount) = BigInt::from_str(&payment.amount) {
+            graph.record_payment(&payment.user, &payment.provider, amount.clone());
+
+            if let Some(usd) = parse_hex_address(&payment.contract_address)
+                .and_then(|token| price_feed.usd_value(&token, &BigDecimal::from(amount)))
+            {
+                total_volume_usd += usd.clone();
+                *edge_volume_usd
+                    .entry((payment.user.clone(), payment.provider.clone()))
+                    .or_insert_with(|| BigDecimal::from(0)) += usd;
+            }
         }
     }
 
@@ -295,8 +504,8 @@ fn map_network_metrics(events: contract::Events) -> Result<analytics::NetworkMet
     }
 
     let total_users = users.len() as u32;
-    let total_providers = providers.len() as u32;
-    let active_pairs = connections.len() as u32;
+    let total_providers = graph.providers().len() as u32;
+    let active_pairs = graph.edge_count() as u32;
     let max_possible_connections = total_users * total_providers;
     let network_density = if max_possible_connections > 0 {
         active_pairs as f64 / max_possible_connections as f64
@@ -304,17 +513,50 @@ fn map_network_metrics(events: contract::Events) -> Result<analytics::NetworkMet
         0.0
     };
 
-    // Get top connections
-    let mut top_connections = Vec::new();
-    for ((user, provider), count) in connections.iter().take(5) {
-        top_connections.push(analytics::UserProviderEdge {
-            user_address: user.clone(),
-            provider_address: provider.clone(),
-            total_volume: "0".to_string(),
-            transaction_count: *count,
-            relationship_strength: (*count as f64) / 10.0,
-        });
-    }
+    let total_volume = graph.total_volume();
+    let total_volume_f64 = total_volume.to_string().parse::<f64>().unwrap_or(0.0);
+
+    let to_proto_edge = |edge: &graph::Edge| {
+        let volume_share = if total_volume_f64 > 0.0 {
+            edge.volume.to_string().parse::<f64>().unwrap_or(0.0) / total_volume_f64
+        } else {
+            0.0
+        };
+
+        analytics::UserProviderEdge {
+            user_address: edge.user.clone(),
+            provider_address: edge.provider.clone(),
+            total_volume: edge.volume.to_string(),
+            total_volume_usd: edge_volume_usd
+                .get(&(edge.user.clone(), edge.provider.clone()))
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "0".to_string()),
+            transaction_count: edge.transaction_count,
+            relationship_strength: volume_share,
+            user_degree: graph.user_degree(&edge.user),
+            provider_degree: graph.provider_degree(&edge.provider),
+        }
+    };
+
+    let top_connections = graph
+        .top_edges_by_volume(NETWORK_TOP_K)
+        .into_iter()
+        .map(to_proto_edge)
+        .collect();
+    let top_connections_by_count = graph
+        .top_edges_by_count(NETWORK_TOP_K)
+        .into_iter()
+        .map(to_proto_edge)
+        .collect();
+
+    let provider_centrality = graph
+        .provider_centrality(CENTRALITY_ITERATIONS, CENTRALITY_DAMPING)
+        .into_iter()
+        .map(|(provider_address, score)| analytics::ProviderCentralityScore {
+            provider_address,
+            score,
+        })
+        .collect();
 
     Ok(analytics::NetworkMetrics {
         total_unique_users: total_users,
@@ -322,17 +564,33 @@ fn map_network_metrics(events: contract::Events) -> Result<analytics::NetworkMet
         active_user_provider_pairs: active_pairs,
         network_density,
         top_connections,
-        total_network_volume: total_volume,
+        top_connections_by_count,
+        provider_centrality,
+        total_network_volume: total_volume.to_string(),
+        total_network_volume_usd: total_volume_usd.to_string(),
     })
 }
 
-/// Comprehensive analytics bundle combining all insights
+/// Comprehensive analytics bundle combining all insights. `price_params` uses the same format as
+/// [`map_payment_analytics`]/[`map_network_metrics`] and populates `token_metrics` with the
+/// configured feed's own price/decimals per token, rather than leaving the field empty.
 #[substreams::handlers::map]
 fn map_analytics_bundle(
+    price_params: String,
     payment_analytics: analytics::PaymentAnalytics,
     anomaly_alerts: analytics::AnomalyAlert,
     network_metrics: analytics::NetworkMetrics,
 ) -> Result<analytics::AnalyticsBundle, substreams::errors::Error> {
+    let price_feed = PriceFeed::from_params(&price_params);
+    let token_metrics = price_feed
+        .entries()
+        .map(|(address, entry)| analytics::TokenMetrics {
+            token_address: Hex(address).to_string(),
+            price_usd: entry.price_usd.to_string(),
+            decimals: entry.decimals,
+        })
+        .collect();
+
     Ok(analytics::AnalyticsBundle {
         payment_analytics: Some(payment_analytics),
         user_metrics: vec![], // Can be populated later
@@ -340,7 +598,7 @@ fn map_analytics_bundle(
         anomaly_alerts: vec![anomaly_alerts],
         predictive_insights: vec![], // Can be populated later
         network_metrics: Some(network_metrics),
-        token_metrics: vec![], // Will be populated with Token API data
+        token_metrics,
         cross_chain_metrics: vec![], // For multi-chain support
     })
 }
@@ -0,0 +1,93 @@
+use crate::hex_address::parse_hex_address;
+use std::collections::HashMap;
+use std::str::FromStr;
+use substreams::scalar::BigDecimal;
+
+/// USD price and decimals for one token, as configured via the module's `params`.
+#[derive(Clone, Debug)]
+pub struct PriceFeedEntry {
+    pub price_usd: BigDecimal,
+    pub decimals: u32,
+}
+
+/// Per-block USD price feed, keyed by token contract address.
+///
+/// Populated from the module's `params` (a comma-separated list of
+/// `0x<address>:<price_usd>:<decimals>` entries) so the same compiled substream can be pointed
+/// at whichever token(s) an escrow deployment settles in without a recompile. Prices are
+/// necessarily a point-in-time snapshot supplied by the caller (e.g. from an oracle substream
+/// or an off-chain feed) rather than derived on-chain.
+#[derive(Default)]
+pub struct PriceFeed {
+    entries: HashMap<[u8; 20], PriceFeedEntry>,
+}
+
+impl PriceFeed {
+    pub fn from_params(params: &str) -> Self {
+        let mut entries = HashMap::new();
+
+        for raw in params.split(',') {
+            if let Some((address, entry)) = parse_entry(raw.trim()) {
+                entries.insert(address, entry);
+            }
+        }
+
+        Self { entries }
+    }
+
+    pub fn get(&self, token: &[u8]) -> Option<&PriceFeedEntry> {
+        self.entries.get(token)
+    }
+
+    /// Every configured token address and its feed entry, for callers (e.g.
+    /// `map_analytics_bundle`'s `token_metrics`) that report on the feed itself rather than
+    /// converting a specific raw amount.
+    pub fn entries(&self) -> impl Iterator<Item = (&[u8; 20], &PriceFeedEntry)> {
+        self.entries.iter()
+    }
+
+    /// Convert a raw token amount into its USD value, scaling by the token's decimals.
+    /// Returns `None` when `token` has no configured price.
+    pub fn usd_value(&self, token: &[u8], raw_amount: &BigDecimal) -> Option<BigDecimal> {
+        let entry = self.get(token)?;
+        let mut scale = BigDecimal::from(1);
+        for _ in 0..entry.decimals {
+            scale = scale * BigDecimal::from(10);
+        }
+        Some(raw_amount.clone() / scale * entry.price_usd.clone())
+    }
+}
+
+fn parse_entry(raw: &str) -> Option<([u8; 20], PriceFeedEntry)> {
+    let mut parts = raw.split(':');
+    let address = parse_hex_address(parts.next()?)?;
+    let price_usd = BigDecimal::from_str(parts.next()?).ok()?;
+    let decimals = parts.next()?.parse::<u32>().ok()?;
+
+    Some((address, PriceFeedEntry { price_usd, decimals }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN_ADDRESS: &str = "0xabababababababababababababababababababab";
+    const OTHER_ADDRESS: &str = "0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd";
+
+    #[test]
+    fn usd_value_scales_by_decimals_and_applies_price() {
+        let feed = PriceFeed::from_params(&format!("{TOKEN_ADDRESS}:2.50:6"));
+        let token = parse_hex_address(TOKEN_ADDRESS).unwrap();
+
+        // 1_000_000 raw units at 6 decimals is 1.0 token, at $2.50/token.
+        let usd = feed.usd_value(&token, &BigDecimal::from(1_000_000)).unwrap();
+        assert_eq!(usd, BigDecimal::from_str("2.50").unwrap());
+    }
+
+    #[test]
+    fn usd_value_is_none_for_an_unconfigured_token() {
+        let feed = PriceFeed::from_params(&format!("{TOKEN_ADDRESS}:2.50:6"));
+        let other_token = parse_hex_address(OTHER_ADDRESS).unwrap();
+        assert!(feed.usd_value(&other_token, &BigDecimal::from(1)).is_none());
+    }
+}
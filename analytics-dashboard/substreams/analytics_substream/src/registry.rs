@@ -0,0 +1,58 @@
+use crate::hex_address::parse_hex_address;
+use hex_literal::hex;
+
+/// Default escrow contract tracked when `params` holds no valid address.
+const DEFAULT_ESCROW_TRACKED_CONTRACT: [u8; 20] = hex!("e73922a448d76756babc9126f4401101cbfb4fbc");
+
+/// Set of escrow contract addresses this substream's analytics/anomaly-detection handlers
+/// (`map_events`, `map_payment_analytics`, ...) index.
+///
+/// Populated from the module's `params` (a comma-separated list of `0x`-prefixed
+/// addresses) so the same compiled substream can track a fleet of deployments
+/// instead of a single hardcoded address.
+pub struct ContractRegistry {
+    addresses: Vec<[u8; 20]>,
+}
+
+impl ContractRegistry {
+    pub fn from_params(params: &str) -> Self {
+        let addresses: Vec<[u8; 20]> = params
+            .split(',')
+            .filter_map(|raw| parse_hex_address(raw.trim()))
+            .collect();
+
+        if addresses.is_empty() {
+            Self { addresses: vec![DEFAULT_ESCROW_TRACKED_CONTRACT] }
+        } else {
+            Self { addresses }
+        }
+    }
+
+    pub fn contains(&self, address: &[u8]) -> bool {
+        self.addresses.iter().any(|tracked| tracked.as_slice() == address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_whitespace_or_garbage_params_fall_back_to_the_default_contract() {
+        for params in ["", "   ", "not-an-address", ",,,"] {
+            let registry = ContractRegistry::from_params(params);
+            assert!(registry.contains(&DEFAULT_ESCROW_TRACKED_CONTRACT));
+        }
+    }
+
+    #[test]
+    fn a_multi_address_list_tracks_every_address_and_nothing_else() {
+        let registry = ContractRegistry::from_params(
+            "0xabababababababababababababababababababab, 0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+        );
+
+        assert!(registry.contains(&parse_hex_address("0xabababababababababababababababababababab").unwrap()));
+        assert!(registry.contains(&parse_hex_address("0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd").unwrap()));
+        assert!(!registry.contains(&DEFAULT_ESCROW_TRACKED_CONTRACT));
+    }
+}
@@ -0,0 +1,206 @@
+/// Number of samples a per-entity detector needs before it starts flagging anomalies.
+pub const DEFAULT_WARMUP_COUNT: u64 = 30;
+
+/// Standard deviations above the mean that count as anomalous by default.
+pub const DEFAULT_Z_SCORE_THRESHOLD: f64 = 3.0;
+
+/// Running mean/variance for one entity's payment amounts, updated online via Welford's
+/// algorithm so the full sample history never needs to be replayed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WelfordStats {
+    pub count: u64,
+    pub mean: f64,
+    pub m2: f64,
+}
+
+impl WelfordStats {
+    /// Fold a new sample into the running statistics.
+    pub fn update(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        let delta2 = x - self.mean;
+        self.m2 += delta * delta2;
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Z-score of `x` against the current distribution, or `None` before `warmup_count`
+    /// samples have been observed or while the distribution has zero spread.
+    pub fn z_score(&self, x: f64, warmup_count: u64) -> Option<f64> {
+        if self.count < warmup_count {
+            return None;
+        }
+        let stddev = self.stddev();
+        if stddev == 0.0 {
+            return None;
+        }
+        Some((x - self.mean) / stddev)
+    }
+}
+
+/// Median and median-absolute-deviation of `samples`, a robust alternative to mean/stddev
+/// for heavy-tailed amount distributions where a handful of large payments would otherwise
+/// drag the Welford mean and variance around.
+pub fn median_absolute_deviation(samples: &mut [f64]) -> (f64, f64) {
+    let median = median(samples);
+    let mut deviations: Vec<f64> = samples.iter().map(|x| (x - median).abs()).collect();
+    (median, median(&mut deviations))
+}
+
+/// Robust z-score using MAD instead of stddev, scaled by the usual 1/0.6745 normal-consistency
+/// constant so it lines up with a standard-normal z-score on Gaussian data.
+pub fn mad_z_score(x: f64, median: f64, mad: f64) -> Option<f64> {
+    if mad == 0.0 {
+        return None;
+    }
+    Some(0.6745 * (x - median) / mad)
+}
+
+/// Order-statistics (percentiles) over a batch of values: a scale-free, skew-robust alternative
+/// to mean/stddev for distributions where a handful of legitimate large values would otherwise
+/// drag the Welford mean and variance around.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quantiles {
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Quantiles {
+    pub fn iqr(&self) -> f64 {
+        self.p75 - self.p25
+    }
+
+    /// Classify `x` against this distribution's IQR fences (`Q3 + 1.5*IQR` mild,
+    /// `Q3 + 3.0*IQR` severe), or `None` if it isn't an outlier.
+    pub fn classify(&self, x: f64) -> Option<AnomalySeverity> {
+        let iqr = self.iqr();
+        if x > self.p75 + 3.0 * iqr {
+            Some(AnomalySeverity::Severe)
+        } else if x > self.p75 + 1.5 * iqr {
+            Some(AnomalySeverity::Mild)
+        } else {
+            None
+        }
+    }
+}
+
+/// How far past the IQR fence a flagged value landed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnomalySeverity {
+    Mild,
+    Severe,
+}
+
+impl AnomalySeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalySeverity::Mild => "mild",
+            AnomalySeverity::Severe => "severe",
+        }
+    }
+}
+
+/// Compute percentiles {25, 50, 75, 90, 95, 99} from `values` via `sorted[len * p / 100]`
+/// indexing. Returns `None` for `len <= 1`, where percentiles aren't meaningful.
+pub fn compute_quantiles(values: &[f64]) -> Option<Quantiles> {
+    if values.len() <= 1 {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    Some(Quantiles {
+        p25: quantile(&sorted, 25),
+        p50: quantile(&sorted, 50),
+        p75: quantile(&sorted, 75),
+        p90: quantile(&sorted, 90),
+        p95: quantile(&sorted, 95),
+        p99: quantile(&sorted, 99),
+    })
+}
+
+fn quantile(sorted: &[f64], p: usize) -> f64 {
+    let idx = (sorted.len() * p / 100).min(sorted.len() - 1);
+    sorted[idx]
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn welford_stats_matches_known_mean_and_stddev() {
+        let mut stats = WelfordStats::default();
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.update(x);
+        }
+        assert_eq!(stats.count, 8);
+        assert!((stats.mean - 5.0).abs() < 1e-9);
+        assert!((stats.stddev() - 2.138_089_935_299_395).abs() < 1e-9);
+    }
+
+    #[test]
+    fn z_score_is_none_before_warmup() {
+        let mut stats = WelfordStats::default();
+        stats.update(100.0);
+        assert_eq!(stats.z_score(1000.0, 30), None);
+    }
+
+    #[test]
+    fn compute_quantiles_is_none_for_single_value() {
+        assert_eq!(compute_quantiles(&[1.0]), None);
+    }
+
+    #[test]
+    fn quantiles_classify_flags_outliers_past_the_iqr_fence() {
+        let mut values: Vec<f64> = (1..=100).map(|x| x as f64).collect();
+        values.push(10_000.0);
+        let quantiles = compute_quantiles(&values).unwrap();
+        assert_eq!(quantiles.classify(10_000.0), Some(AnomalySeverity::Severe));
+        assert_eq!(quantiles.classify(50.0), None);
+    }
+
+    #[test]
+    fn median_absolute_deviation_and_mad_z_score_agree_on_a_symmetric_sample() {
+        let mut samples = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let (median, mad) = median_absolute_deviation(&mut samples);
+        assert_eq!(median, 3.0);
+        assert_eq!(mad, 1.0);
+        assert!(mad_z_score(3.0, median, mad).unwrap().abs() < 1e-9);
+        assert!(mad_z_score(5.0, median, mad).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn mad_z_score_is_none_for_a_zero_mad() {
+        assert_eq!(mad_z_score(1.0, 1.0, 0.0), None);
+    }
+}
@@ -1,10 +1,210 @@
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use primitive_types::U256;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Token API configuration
 const TOKEN_API_ENDPOINT: &str = "https://api.thegraph.com/tokens";
 
+/// Fallback price endpoint (a Uniswap/Chainlink-style relay), queried alongside The Graph on
+/// every price lookup so a price is still available when The Graph is down or returns nothing.
+const FALLBACK_PRICE_ENDPOINT: &str = "https://api.onchain-price-relay.example/v1/price";
+
+/// Quotes older than this are discarded by [`PriceOracle::aggregate_price`] rather than used,
+/// so a wedged source can't silently hand back a months-old price.
+const DEFAULT_PRICE_STALENESS_SECS: u64 = 300;
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A single source's price observation, carrying the time it was observed so the oracle can
+/// reject it once it's too stale to trust.
+#[derive(Debug, Clone, Copy)]
+pub struct PriceQuote {
+    pub price_usd: f64,
+    pub observed_at_unix: u64,
+}
+
+/// One backend capable of quoting a token's USD price. Implementations are registered with a
+/// [`PriceOracle`] in priority order — e.g. The Graph as primary, a Uniswap/Chainlink-style
+/// feed as fallback — so a single source going down degrades rather than zeroes out pricing.
+#[async_trait::async_trait]
+pub trait PriceSource: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn fetch_price(&self, token_address: &str) -> Option<PriceQuote>;
+}
+
+/// Queries registered [`PriceSource`]s for a token, discards quotes older than
+/// `max_staleness_secs`, and aggregates the survivors by median so no single source (primary
+/// or fallback) can swing the reported price on its own.
+pub struct PriceOracle {
+    sources: Vec<Box<dyn PriceSource>>,
+    max_staleness_secs: u64,
+}
+
+impl PriceOracle {
+    pub fn new(sources: Vec<Box<dyn PriceSource>>, max_staleness_secs: u64) -> Self {
+        Self { sources, max_staleness_secs }
+    }
+
+    /// Query every source for `token_address` and return `(median_price, agreeing_sources)`,
+    /// or `None` if every source failed or returned only stale quotes.
+    pub async fn aggregate_price(&self, token_address: &str) -> Option<(f64, u32)> {
+        let now = unix_now();
+        let mut fresh_prices: Vec<f64> = Vec::new();
+
+        for source in &self.sources {
+            match source.fetch_price(token_address).await {
+                Some(quote) if now.saturating_sub(quote.observed_at_unix) <= self.max_staleness_secs => {
+                    fresh_prices.push(quote.price_usd);
+                }
+                Some(quote) => eprintln!(
+                    "Discarding stale quote from {}: {}s old",
+                    source.name(),
+                    now.saturating_sub(quote.observed_at_unix)
+                ),
+                None => eprintln!("Price source {} returned no quote for {}", source.name(), token_address),
+            }
+        }
+
+        if fresh_prices.is_empty() {
+            return None;
+        }
+
+        let confidence = fresh_prices.len() as u32;
+        Some((median_price(&mut fresh_prices), confidence))
+    }
+}
+
+/// Median of `prices`, sorting in place. Split out of `aggregate_price` so the aggregation math
+/// can be unit tested without an async runtime or a fake [`PriceSource`].
+fn median_price(prices: &mut [f64]) -> f64 {
+    prices.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        (prices[mid - 1] + prices[mid]) / 2.0
+    } else {
+        prices[mid]
+    }
+}
+
+/// Primary price source: the same Graph Token API endpoint `get_tokens_metadata` uses for
+/// symbol/decimals/market data, queried here per-address so it can be timed out and replaced
+/// independently of the batched metadata call.
+pub struct GraphPriceSource {
+    client: reqwest::Client,
+}
+
+impl GraphPriceSource {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for GraphPriceSource {
+    fn name(&self) -> &'static str {
+        "the_graph"
+    }
+
+    async fn fetch_price(&self, token_address: &str) -> Option<PriceQuote> {
+        let url = format!("{}?addresses={}", TOKEN_API_ENDPOINT, token_address);
+        let response = self.client.get(&url).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let api_response = response.json::<TokenAPIResponse>().await.ok()?;
+        let token = api_response.data.into_iter().next()?;
+        Some(PriceQuote {
+            price_usd: token.price.usd,
+            observed_at_unix: unix_now(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FallbackPriceResponse {
+    price_usd: f64,
+    updated_at_unix: u64,
+}
+
+/// Fallback price source backed by an on-chain-style DEX/oracle feed (e.g. a Uniswap TWAP or
+/// Chainlink aggregator). [`PriceOracle::aggregate_price`] queries it on every lookup, so it
+/// still yields a quote when The Graph is unreachable or empty.
+pub struct OnChainFallbackPriceSource {
+    client: reqwest::Client,
+}
+
+impl OnChainFallbackPriceSource {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for OnChainFallbackPriceSource {
+    fn name(&self) -> &'static str {
+        "onchain_fallback"
+    }
+
+    async fn fetch_price(&self, token_address: &str) -> Option<PriceQuote> {
+        let url = format!("{}?token={}", FALLBACK_PRICE_ENDPOINT, token_address);
+        let response = self.client.get(&url).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let parsed = response.json::<FallbackPriceResponse>().await.ok()?;
+        Some(PriceQuote {
+            price_usd: parsed.price_usd,
+            observed_at_unix: parsed.updated_at_unix,
+        })
+    }
+}
+
+/// A raw on-chain integer amount that may arrive as either a `0x`-prefixed hex string (event
+/// logs) or a plain decimal string (API payloads). Parsing straight into `U256` keeps the
+/// exact integer value intact instead of round-tripping through a lossy `f64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexOrDecimalU256(pub U256);
+
+impl HexOrDecimalU256 {
+    pub fn parse(raw: &str) -> Option<U256> {
+        match raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X")) {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_dec_str(raw).ok(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HexOrDecimalU256 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        HexOrDecimalU256::parse(&raw)
+            .map(HexOrDecimalU256)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid hex-or-decimal amount: {raw}")))
+    }
+}
+
+/// Scale a raw integer `amount` (e.g. wei) down to its human-readable `BigDecimal` value given
+/// the token's `decimals`, exactly — unlike `amount as f64 / 10_f64.powi(decimals)`, which
+/// silently loses precision beyond ~15 significant digits for 18-decimal ERC-20 amounts.
+fn scale_by_decimals(amount: U256, decimals: u32) -> BigDecimal {
+    let digits = BigInt::from_str(&amount.to_string()).unwrap_or_default();
+    BigDecimal::new(digits, decimals as i64)
+}
+
 /// Token metadata from The Graph Token API
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TokenMetadata {
@@ -13,6 +213,10 @@ pub struct TokenMetadata {
     pub name: String,
     pub decimals: u32,
     pub current_price_usd: f64,
+    /// Number of [`PriceSource`]s that agreed on `current_price_usd` within the staleness
+    /// window; 0 means every oracle source (fallback included) failed or was stale and this is
+    /// the Graph batch endpoint's own uncorroborated quote.
+    pub price_confidence: u32,
     pub market_cap: Option<f64>,
     pub holder_count: Option<u64>,
     pub volume_24h: Option<f64>,
@@ -52,52 +256,102 @@ struct MarketData {
 /// Token API client for fetching enriched token data
 pub struct TokenAPIClient {
     client: reqwest::Client,
+    price_oracle: PriceOracle,
 }
 
 impl TokenAPIClient {
     pub fn new() -> Self {
-        Self {
-            client: reqwest::Client::new(),
-        }
+        let client = reqwest::Client::new();
+        let price_oracle = PriceOracle::new(
+            vec![
+                Box::new(GraphPriceSource::new(client.clone())),
+                Box::new(OnChainFallbackPriceSource::new(client.clone())),
+            ],
+            DEFAULT_PRICE_STALENESS_SECS,
+        );
+        Self { client, price_oracle }
     }
 
-    /// Fetch token metadata for multiple addresses
+    /// Fetch token metadata for multiple addresses. Symbol/name/decimals/market data come from
+    /// the Graph batch endpoint when it succeeds; price and `price_confidence` come from
+    /// `price_oracle`, which is queried independently of that batch call. When the oracle has no
+    /// fresh quote for an address the batch returned, its batch price is kept with confidence 0;
+    /// an address the batch did not return and the oracle cannot price is left out of the map.
+    /// This way a Graph outage or a partial response still yields a price for every address the
+    /// oracle's sources can price, instead of the whole map coming back empty.
     pub async fn get_tokens_metadata(&self, addresses: &[String]) -> Result<HashMap<String, TokenMetadata>, Box<dyn std::error::Error>> {
         let mut metadata = HashMap::new();
-        
-        // Batch request for multiple tokens
+
+        // Batch request for multiple tokens. A failure here only costs us symbol/name/decimals/
+        // market data for the affected addresses, not price: the oracle pass below still runs.
         let addresses_param = addresses.join(",");
         let url = format!("{}?addresses={}", TOKEN_API_ENDPOINT, addresses_param);
-        
-        match self.client.get(&url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.json::<TokenAPIResponse>().await {
-                        Ok(api_response) => {
-                            for token in api_response.data {
-                                metadata.insert(token.address.clone(), TokenMetadata {
-                                    address: token.address,
-                                    symbol: token.symbol,
-                                    name: token.name,
-                                    decimals: token.decimals,
-                                    current_price_usd: token.price.usd,
-                                    market_cap: token.market_data.as_ref().and_then(|m| m.market_cap),
-                                    holder_count: token.market_data.as_ref().and_then(|m| m.holder_count),
-                                    volume_24h: token.market_data.as_ref().and_then(|m| m.volume_24h),
-                                    price_change_24h: token.price.change_24h,
-                                    logo_uri: None, // Would be included in actual API response
-                                });
-                            }
-                        }
-                        Err(e) => eprintln!("Failed to parse Token API response: {}", e),
-                    }
-                } else {
-                    eprintln!("Token API request failed with status: {}", response.status());
+
+        let api_tokens = match self.client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => match response.json::<TokenAPIResponse>().await {
+                Ok(api_response) => api_response.data,
+                Err(e) => {
+                    eprintln!("Failed to parse Token API response: {}", e);
+                    Vec::new()
                 }
+            },
+            Ok(response) => {
+                eprintln!("Token API request failed with status: {}", response.status());
+                Vec::new()
+            }
+            Err(e) => {
+                eprintln!("Token API request error: {}", e);
+                Vec::new()
+            }
+        };
+
+        for token in api_tokens {
+            let (price_usd, price_confidence) = match self.price_oracle.aggregate_price(&token.address).await {
+                Some((price, confidence)) => (price, confidence),
+                // Every price source failed or was stale; keep serving the
+                // Graph batch's own quote rather than zeroing it out.
+                None => (token.price.usd, 0),
+            };
+
+            metadata.insert(token.address.clone(), TokenMetadata {
+                address: token.address,
+                symbol: token.symbol,
+                name: token.name,
+                decimals: token.decimals,
+                current_price_usd: price_usd,
+                price_confidence,
+                market_cap: token.market_data.as_ref().and_then(|m| m.market_cap),
+                holder_count: token.market_data.as_ref().and_then(|m| m.holder_count),
+                volume_24h: token.market_data.as_ref().and_then(|m| m.volume_24h),
+                price_change_24h: token.price.change_24h,
+                logo_uri: None, // Would be included in actual API response
+            });
+        }
+
+        // Any address the Graph batch call didn't cover — because it failed outright, returned
+        // a non-2xx, or simply omitted it — still gets priced directly from the oracle, with a
+        // minimal synthesized `TokenMetadata` standing in for the missing market data.
+        for address in addresses {
+            if metadata.contains_key(address) {
+                continue;
+            }
+            if let Some((price_usd, price_confidence)) = self.price_oracle.aggregate_price(address).await {
+                metadata.insert(address.clone(), TokenMetadata {
+                    address: address.clone(),
+                    symbol: "UNKNOWN".to_string(),
+                    name: "Unknown Token".to_string(),
+                    decimals: 18,
+                    current_price_usd: price_usd,
+                    price_confidence,
+                    market_cap: None,
+                    holder_count: None,
+                    volume_24h: None,
+                    price_change_24h: None,
+                    logo_uri: None,
+                });
             }
-            Err(e) => eprintln!("Token API request error: {}", e),
         }
-        
+
         Ok(metadata)
     }
 
@@ -117,10 +371,11 @@ impl TokenAPIClient {
         let mut costs = Vec::new();
         for (token_addr, amount) in amounts {
             if let Some(token_meta) = metadata.get(token_addr) {
-                if let Ok(amount_f64) = amount.parse::<f64>() {
-                    let adjusted_amount = amount_f64 / 10_f64.powi(token_meta.decimals as i32);
-                    let usd_cost = adjusted_amount * token_meta.current_price_usd;
-                    
+                if let Some(raw_amount) = HexOrDecimalU256::parse(amount) {
+                    let adjusted_amount = scale_by_decimals(raw_amount, token_meta.decimals);
+                    let price = BigDecimal::try_from(token_meta.current_price_usd).unwrap_or_default();
+                    let usd_cost = adjusted_amount.clone() * price;
+
                     costs.push(PaymentCost {
                         token_address: token_addr.clone(),
                         token_symbol: token_meta.symbol.clone(),
@@ -132,7 +387,7 @@ impl TokenAPIClient {
                 }
             }
         }
-        
+
         Ok(costs)
     }
 }
@@ -142,8 +397,8 @@ impl TokenAPIClient {
 pub struct PaymentCost {
     pub token_address: String,
     pub token_symbol: String,
-    pub amount: f64,
-    pub usd_value: f64,
+    pub amount: BigDecimal,
+    pub usd_value: BigDecimal,
     pub price_per_token: f64,
     pub efficiency_score: f64, // 0-1 score for cost efficiency
 }
@@ -175,6 +430,64 @@ fn calculate_efficiency_score(token: &TokenMetadata) -> f64 {
     score.max(0.0).min(1.0)
 }
 
+/// One historical batch payment, carrying the priority fee (tip-per-gas, in wei) its
+/// transaction paid so [`CostOptimizer`] can derive real gas-fee recommendations instead of a
+/// fixed savings multiplier.
+#[derive(Debug, Clone)]
+pub struct PaymentHistoryEntry {
+    pub token_address: String,
+    pub amount: String,
+    pub timestamp: u64,
+    pub priority_fee_per_gas: u64,
+    pub num_calls: u64,
+}
+
+/// Percentile summary of observed priority fees (tip-per-gas, in wei) over a time window,
+/// computed by indexing a sorted `Vec<u64>` at `len * p / 100`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrioFeeData {
+    pub min: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+/// Gas spent by a standalone (non-batched) transaction; batching `n` calls into one avoids
+/// `n - 1` of these, which is what translates a recommended tip into a concrete savings figure.
+const BASE_TX_OVERHEAD_GAS: u64 = 21_000;
+
+/// Saturating `calls_saved * BASE_TX_OVERHEAD_GAS * tip_wei`: all three factors come from
+/// observed on-chain data (call counts, a fixed gas constant, and a priority fee in wei), so a
+/// pathological history shouldn't be able to wrap a `u64` savings estimate around to a tiny or
+/// negative-looking number.
+fn savings_wei(calls_saved: u64, tip_wei: u64) -> u64 {
+    (calls_saved as u128)
+        .saturating_mul(BASE_TX_OVERHEAD_GAS as u128)
+        .saturating_mul(tip_wei as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+fn compute_prio_fee_data(fees: &[u64]) -> Option<PrioFeeData> {
+    if fees.is_empty() {
+        return None;
+    }
+
+    let mut sorted = fees.to_vec();
+    sorted.sort_unstable();
+    let percentile = |p: usize| sorted[(sorted.len() * p / 100).min(sorted.len() - 1)];
+
+    Some(PrioFeeData {
+        min: sorted[0],
+        p50: percentile(50),
+        p75: percentile(75),
+        p90: percentile(90),
+        p95: percentile(95),
+        max: *sorted.last().unwrap(),
+    })
+}
+
 /// AI-powered cost optimization recommendations
 pub struct CostOptimizer {
     token_client: TokenAPIClient,
@@ -188,16 +501,20 @@ impl CostOptimizer {
     }
 
     /// Generate cost optimization suggestions
-    pub async fn optimize_payments(&self, payment_history: &[(String, String, u64)]) -> Result<Vec<OptimizationSuggestion>, Box<dyn std::error::Error>> {
+    pub async fn optimize_payments(&self, payment_history: &[PaymentHistoryEntry]) -> Result<Vec<OptimizationSuggestion>, Box<dyn std::error::Error>> {
         let mut suggestions = Vec::new();
-        
+
         // Analyze payment patterns
-        let mut token_usage: HashMap<String, (f64, u32)> = HashMap::new();
-        for (token_addr, amount, _timestamp) in payment_history {
-            if let Ok(amount_f64) = amount.parse::<f64>() {
-                let entry = token_usage.entry(token_addr.clone()).or_insert((0.0, 0));
-                entry.0 += amount_f64;
-                entry.1 += 1;
+        let mut token_usage: HashMap<String, (U256, u32, u64, Vec<u64>)> = HashMap::new();
+        for entry in payment_history {
+            if let Some(raw_amount) = HexOrDecimalU256::parse(&entry.amount) {
+                let agg = token_usage
+                    .entry(entry.token_address.clone())
+                    .or_insert((U256::zero(), 0, 0, Vec::new()));
+                agg.0 += raw_amount;
+                agg.1 += 1;
+                agg.2 += entry.num_calls;
+                agg.3.push(entry.priority_fee_per_gas);
             }
         }
 
@@ -206,27 +523,50 @@ impl CostOptimizer {
         let metadata = self.token_client.get_tokens_metadata(&token_addresses).await?;
 
         // Generate suggestions based on usage patterns and market data
-        for (token_addr, (total_amount, payment_count)) in token_usage {
+        for (token_addr, (total_amount_raw, payment_count, total_num_calls, priority_fees)) in token_usage {
             if let Some(token_meta) = metadata.get(&token_addr) {
-                let avg_payment = total_amount / payment_count as f64;
-                
-                // Suggest batching for frequent small payments
-                if payment_count > 10 && avg_payment < 100.0 {
-                    suggestions.push(OptimizationSuggestion {
-                        suggestion_type: "batch_payments".to_string(),
-                        description: format!("Consider batching your {} payments to save on gas costs", token_meta.symbol),
-                        potential_savings_usd: avg_payment * 0.1 * payment_count as f64,
-                        confidence: 0.8,
-                        token_address: Some(token_addr.clone()),
-                    });
+                let total_amount = scale_by_decimals(total_amount_raw, token_meta.decimals);
+                let avg_payment = total_amount.clone() / BigDecimal::from(payment_count);
+
+                // Suggest batching for frequent small payments, with a tip recommendation and
+                // savings estimate derived from the transactions' own observed priority fees
+                // rather than a fixed multiplier.
+                if payment_count > 10 && avg_payment < BigDecimal::from(100) {
+                    if let Some(fee_data) = compute_prio_fee_data(&priority_fees) {
+                        // Batching merges separate historical transactions into one, so the
+                        // number of base-tx overheads avoided tracks payment_count (how many
+                        // transactions there were), not total_num_calls (how many API calls
+                        // those transactions' batches already covered between them).
+                        let calls_saved = (payment_count as u64).saturating_sub(1);
+                        for (label, tip_wei, inclusion) in [
+                            ("fast", fee_data.p90, "fast inclusion"),
+                            ("economical", fee_data.p50, "economical inclusion"),
+                        ] {
+                            let savings_wei = savings_wei(calls_saved, tip_wei);
+                            suggestions.push(OptimizationSuggestion {
+                                suggestion_type: format!("batch_payments_{label}"),
+                                description: format!(
+                                    "Batch your {} payments with a {tip_wei} wei/gas tip for {inclusion} \
+                                     (observed p50={} p90={} p95={} wei/gas)",
+                                    token_meta.symbol, fee_data.p50, fee_data.p90, fee_data.p95
+                                ),
+                                potential_savings_usd: 0.0,
+                                potential_savings_wei: Some(savings_wei),
+                                confidence: 0.8,
+                                token_address: Some(token_addr.clone()),
+                            });
+                        }
+                    }
                 }
 
                 // Suggest alternative tokens with better efficiency
                 if token_meta.current_price_usd > 1.0 && token_meta.volume_24h.unwrap_or(0.0) < 100_000.0 {
+                    let savings = &total_amount * BigDecimal::try_from(0.05).unwrap_or_default();
                     suggestions.push(OptimizationSuggestion {
                         suggestion_type: "alternative_token".to_string(),
                         description: format!("Consider using PYUSD instead of {} for better liquidity", token_meta.symbol),
-                        potential_savings_usd: total_amount * 0.05,
+                        potential_savings_usd: savings.to_f64().unwrap_or(0.0),
+                        potential_savings_wei: None,
                         confidence: 0.6,
                         token_address: Some(token_addr.clone()),
                     });
@@ -235,10 +575,13 @@ impl CostOptimizer {
                 // Timing suggestions based on price volatility
                 if let Some(change_24h) = token_meta.price_change_24h {
                     if change_24h < -5.0 {
+                        let savings = &total_amount
+                            * BigDecimal::try_from(change_24h.abs() / 100.0).unwrap_or_default();
                         suggestions.push(OptimizationSuggestion {
                             suggestion_type: "timing_optimization".to_string(),
                             description: format!("{} is down {}% - good time to deposit", token_meta.symbol, change_24h.abs()),
-                            potential_savings_usd: total_amount * (change_24h.abs() / 100.0),
+                            potential_savings_usd: savings.to_f64().unwrap_or(0.0),
+                            potential_savings_wei: None,
                             confidence: 0.7,
                             token_address: Some(token_addr),
                         });
@@ -254,9 +597,54 @@ impl CostOptimizer {
 /// Optimization suggestion from AI analysis
 #[derive(Debug, Serialize, Clone)]
 pub struct OptimizationSuggestion {
-    pub suggestion_type: String, // "batch_payments", "alternative_token", "timing_optimization"
+    pub suggestion_type: String, // "batch_payments_fast", "batch_payments_economical", "alternative_token", "timing_optimization"
     pub description: String,
     pub potential_savings_usd: f64,
+    /// Gas-fee-derived savings (in wei) for `batch_payments_*` suggestions, where the saving is
+    /// denominated in gas rather than USD; `None` for suggestion types priced in USD.
+    pub potential_savings_wei: Option<u64>,
     pub confidence: f64, // 0-1 confidence score
     pub token_address: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_price_averages_the_middle_two_on_an_even_count() {
+        let mut prices = vec![1.0, 3.0, 2.0, 4.0];
+        assert_eq!(median_price(&mut prices), 2.5);
+    }
+
+    #[test]
+    fn median_price_picks_the_middle_one_on_an_odd_count() {
+        let mut prices = vec![5.0, 1.0, 3.0];
+        assert_eq!(median_price(&mut prices), 3.0);
+    }
+
+    #[test]
+    fn hex_or_decimal_u256_parses_both_forms() {
+        assert_eq!(HexOrDecimalU256::parse("0x2a"), Some(U256::from(42)));
+        assert_eq!(HexOrDecimalU256::parse("42"), Some(U256::from(42)));
+        assert_eq!(HexOrDecimalU256::parse("not a number"), None);
+    }
+
+    #[test]
+    fn compute_prio_fee_data_percentiles_a_sorted_copy() {
+        let data = compute_prio_fee_data(&[30, 10, 20]).unwrap();
+        assert_eq!(data.min, 10);
+        assert_eq!(data.max, 30);
+    }
+
+    #[test]
+    fn compute_prio_fee_data_is_none_for_empty_input() {
+        assert!(compute_prio_fee_data(&[]).is_none());
+    }
+
+    #[test]
+    fn savings_wei_saturates_instead_of_wrapping() {
+        assert_eq!(savings_wei(u64::MAX, u64::MAX), u64::MAX);
+        assert_eq!(savings_wei(2, 5), 2 * BASE_TX_OVERHEAD_GAS * 5);
+    }
 }
\ No newline at end of file